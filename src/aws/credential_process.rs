@@ -0,0 +1,44 @@
+use std::convert::TryFrom;
+
+use anyhow::{anyhow, Result};
+use rusoto_sts::Credentials;
+use serde::Serialize;
+
+/// Output shape expected by AWS SDKs from a `credential_process` hook.
+///
+/// See <https://docs.aws.amazon.com/sdkref/latest/guide/feature-process-credentials.html>.
+#[derive(Debug, Serialize)]
+pub struct CredentialProcessOutput {
+    #[serde(rename = "Version")]
+    pub version: u8,
+
+    #[serde(rename = "AccessKeyId")]
+    pub access_key_id: String,
+
+    #[serde(rename = "SecretAccessKey")]
+    pub secret_access_key: String,
+
+    #[serde(rename = "SessionToken")]
+    pub session_token: String,
+
+    #[serde(rename = "Expiration")]
+    pub expiration: String,
+}
+
+impl TryFrom<Credentials> for CredentialProcessOutput {
+    type Error = anyhow::Error;
+
+    fn try_from(credentials: Credentials) -> Result<Self> {
+        if credentials.expiration.is_empty() {
+            return Err(anyhow!("Assumed role credentials had no expiration"));
+        }
+
+        Ok(CredentialProcessOutput {
+            version: 1,
+            access_key_id: credentials.access_key_id,
+            secret_access_key: credentials.secret_access_key,
+            session_token: credentials.session_token,
+            expiration: credentials.expiration,
+        })
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::env;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use ini::Ini;
+use rusoto_sts::Credentials as StsCredentials;
+
+const AWS_ACCESS_KEY_ID: &str = "aws_access_key_id";
+const AWS_SECRET_ACCESS_KEY: &str = "aws_secret_access_key";
+const AWS_SESSION_TOKEN: &str = "aws_session_token";
+const X_SECURITY_TOKEN_EXPIRES: &str = "x_security_token_expires";
+
+/// A thin wrapper around the shared `~/.aws/credentials` file.
+pub struct CredentialsStore {
+    pub profiles: Profiles,
+    path: PathBuf,
+}
+
+impl CredentialsStore {
+    pub fn new() -> Result<Self> {
+        let path = credentials_path()?;
+
+        let ini = if path.exists() {
+            Ini::load_from_file(&path).with_context(|| format!("Error reading {:?}", path))?
+        } else {
+            Ini::new()
+        };
+
+        Ok(CredentialsStore {
+            profiles: Profiles(ini),
+            path,
+        })
+    }
+
+    pub fn save(&mut self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Error creating {:?}", parent))?;
+        }
+
+        self.profiles
+            .0
+            .write_to_file(&self.path)
+            .with_context(|| format!("Error writing {:?}", self.path))
+    }
+}
+
+fn credentials_path() -> Result<PathBuf> {
+    if let Ok(path) = env::var("AWS_SHARED_CREDENTIALS_FILE") {
+        return Ok(PathBuf::from(path));
+    }
+
+    let home = dirs::home_dir().ok_or_else(|| anyhow!("Could not find home directory"))?;
+
+    Ok(home.join(".aws").join("credentials"))
+}
+
+pub struct Profiles(Ini);
+
+impl Profiles {
+    pub fn set_sts_credentials(&mut self, name: String, credentials: Credentials) -> Result<()> {
+        {
+            let mut section = self.0.with_section(Some(name.clone()));
+            section
+                .set(AWS_ACCESS_KEY_ID, credentials.aws_access_key_id)
+                .set(AWS_SECRET_ACCESS_KEY, credentials.aws_secret_access_key)
+                .set(AWS_SESSION_TOKEN, credentials.aws_session_token);
+        }
+
+        if let Some(expiration) = credentials.x_security_token_expires {
+            self.0
+                .with_section(Some(name))
+                .set(X_SECURITY_TOKEN_EXPIRES, expiration.to_rfc3339());
+        }
+
+        Ok(())
+    }
+
+    pub fn get_sts_credentials(&self, name: &str) -> Option<Credentials> {
+        let section = self.0.section(Some(name))?;
+
+        Some(Credentials {
+            aws_access_key_id: section.get(AWS_ACCESS_KEY_ID)?.to_string(),
+            aws_secret_access_key: section.get(AWS_SECRET_ACCESS_KEY)?.to_string(),
+            aws_session_token: section.get(AWS_SESSION_TOKEN)?.to_string(),
+            x_security_token_expires: section
+                .get(X_SECURITY_TOKEN_EXPIRES)
+                .and_then(|expiration| DateTime::parse_from_rfc3339(expiration).ok())
+                .map(|expiration| expiration.with_timezone(&Utc)),
+        })
+    }
+
+    /// Profile sections currently in the store, keyed by profile name.
+    /// Sections without an access key id, secret access key and session token
+    /// are left out.
+    pub fn all_sts_credentials(&self) -> HashMap<String, Credentials> {
+        self.0
+            .sections()
+            .flatten()
+            .filter_map(|name| {
+                self.get_sts_credentials(name)
+                    .map(|credentials| (name.to_string(), credentials))
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Credentials {
+    pub aws_access_key_id: String,
+    pub aws_secret_access_key: String,
+    pub aws_session_token: String,
+    pub x_security_token_expires: Option<DateTime<Utc>>,
+}
+
+impl From<StsCredentials> for Credentials {
+    fn from(credentials: StsCredentials) -> Self {
+        let x_security_token_expires = DateTime::parse_from_rfc3339(&credentials.expiration)
+            .ok()
+            .map(|expiration| expiration.with_timezone(&Utc));
+
+        Credentials {
+            aws_access_key_id: credentials.access_key_id,
+            aws_secret_access_key: credentials.secret_access_key,
+            aws_session_token: credentials.session_token,
+            x_security_token_expires,
+        }
+    }
+}
+
+impl TryFrom<Credentials> for StsCredentials {
+    type Error = anyhow::Error;
+
+    fn try_from(credentials: Credentials) -> Result<Self> {
+        let expiration = credentials
+            .x_security_token_expires
+            .ok_or_else(|| anyhow!("Cached credentials have no recorded expiration"))?;
+
+        Ok(StsCredentials {
+            access_key_id: credentials.aws_access_key_id,
+            secret_access_key: credentials.aws_secret_access_key,
+            session_token: credentials.aws_session_token,
+            expiration: expiration.to_rfc3339(),
+        })
+    }
+}
@@ -7,6 +7,7 @@ use crate::{aws::role::assume_role, saml::Response};
 
 use self::role::Role;
 
+pub mod credential_process;
 pub mod credentials;
 pub mod role;
 
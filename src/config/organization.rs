@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+
+use crate::{
+    aws::credentials::Credentials as CachedCredentials,
+    config::profile::{Profile, ProfileConfig},
+    okta::{applications::AppLink, client::Client as OktaClient},
+};
+
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use futures::future::join_all;
+use glob::Pattern;
+use rusoto_sts::Credentials;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct OrganizationConfig {
+    pub name: String,
+    pub username: String,
+    pub role: Option<String>,
+    pub duration_seconds: Option<i64>,
+    pub profiles: HashMap<String, ProfileConfig>,
+
+    /// Maps Okta group names to the role (name or ARN) a member of that
+    /// group should assume, used to auto-select a role for profiles whose
+    /// SAML response offers more than one.
+    pub role_mappings: Option<HashMap<String, String>>,
+}
+
+impl OrganizationConfig {
+    #[instrument(skip(client, default_role), fields(organization = %client.base_url))]
+    pub async fn from_organization(
+        client: &OktaClient,
+        username: String,
+        default_role: Option<String>,
+    ) -> Result<Self> {
+        let app_links: Vec<AppLink> = client
+            .app_links(None)
+            .await?
+            .into_iter()
+            .filter(|link| link.app_name == "amazon_aws")
+            .collect();
+
+        let profile_futures = app_links
+            .into_iter()
+            .map(|link| ProfileConfig::from_app_link(client, link, default_role.clone()));
+
+        let profiles = join_all(profile_futures)
+            .await
+            .into_iter()
+            .collect::<Result<HashMap<String, ProfileConfig>>>()?;
+
+        Ok(OrganizationConfig {
+            name: client.base_url.to_string(),
+            username,
+            role: default_role,
+            duration_seconds: None,
+            profiles,
+            role_mappings: None,
+        })
+    }
+
+    fn into_profiles(self, pattern: Pattern) -> impl Iterator<Item = Profile> {
+        let default_role = self.role;
+        let default_duration_seconds = self.duration_seconds;
+
+        self.profiles
+            .into_iter()
+            .filter(move |(name, _)| pattern.matches(name))
+            .filter_map(move |(name, config)| {
+                match Profile::try_from_config(
+                    &config,
+                    name.clone(),
+                    default_role.clone(),
+                    default_duration_seconds,
+                ) {
+                    Ok(profile) => Some(profile),
+                    Err(e) => {
+                        warn!("Error building profile {} ({})", name, e);
+                        None
+                    }
+                }
+            })
+    }
+
+    /// Assumes a role for every profile matching `profiles`, skipping any
+    /// profile whose cached credentials (from `cached`) don't expire within
+    /// `refresh_margin` of now. Profiles that were attempted are always
+    /// present in the returned map, keyed by name, with the per-profile
+    /// `Result` of the assumption so callers can distinguish "no such
+    /// profile" from "profile exists but assumption failed".
+    #[instrument(skip(self, client, cached), fields(organization = %self.name))]
+    pub async fn into_credentials(
+        self,
+        client: &OktaClient,
+        profiles: Pattern,
+        cached: &HashMap<String, CachedCredentials>,
+        refresh_margin: Duration,
+    ) -> HashMap<String, Result<Credentials>> {
+        let deadline = Utc::now() + refresh_margin;
+        let role_mappings = self.role_mappings.clone();
+
+        let groups = if role_mappings.is_some() {
+            match client.groups(None).await {
+                Ok(groups) => groups.into_iter().map(|group| group.profile.name).collect(),
+                Err(e) => {
+                    warn!("Error fetching Okta groups ({})", e);
+                    Vec::new()
+                }
+            }
+        } else {
+            Vec::new()
+        };
+
+        let profile_futures = self.into_profiles(profiles).filter_map(|profile| {
+            if let Some(expiration) = cached
+                .get(&profile.name)
+                .and_then(|credentials| credentials.x_security_token_expires)
+            {
+                if expiration > deadline {
+                    info!(
+                        "Skipping {} - cached credentials valid until {}",
+                        profile.name, expiration
+                    );
+                    return None;
+                }
+            }
+
+            let name = profile.name.clone();
+            let role_mappings = role_mappings.clone();
+            let groups = &groups;
+            Some(async move {
+                let result = profile
+                    .into_credentials(client, role_mappings.as_ref(), groups)
+                    .await;
+
+                (name, result)
+            })
+        });
+
+        join_all(profile_futures).await.into_iter().collect()
+    }
+}
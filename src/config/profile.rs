@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use crate::{
     aws::{get_account_alias, role::Role},
     okta::{applications::AppLink, client::Client as OktaClient},
@@ -6,7 +8,9 @@ use crate::{
 };
 
 use anyhow::{anyhow, Result};
-use rusoto_sts::Credentials;
+use rusoto_core::{HttpClient, Region};
+use rusoto_credential::StaticProvider;
+use rusoto_sts::{AssumeRoleRequest, Credentials, Sts, StsClient};
 use serde::{Deserialize, Serialize};
 use tracing::instrument;
 
@@ -88,11 +92,59 @@ impl ProfileConfig {
     }
 }
 
+/// Picks a role from `roles`, preferring one whose name or ARN is mapped to
+/// by one of `groups` in `role_mappings`, and falling back to an interactive
+/// prompt when no mapping matches.
+fn select_role(
+    roles: Vec<Role>,
+    role_mappings: Option<&HashMap<String, String>>,
+    groups: &[String],
+    profile_name: &str,
+) -> Result<Role> {
+    match roles.len() {
+        0 => Err(anyhow!("No role found for profile {}", profile_name)),
+        1 => Ok(roles.into_iter().next().unwrap()),
+        _ => {
+            if let Some(role_mappings) = role_mappings {
+                let mapped_role = groups
+                    .iter()
+                    .filter_map(|group| role_mappings.get(group))
+                    .find_map(|mapped| {
+                        roles
+                            .iter()
+                            .find(|role| {
+                                role.role_name().map(|name| name == mapped).unwrap_or(false)
+                                    || role.role_arn == *mapped
+                            })
+                            .cloned()
+                    });
+
+                if let Some(role) = mapped_role {
+                    return Ok(role);
+                }
+            }
+
+            select(
+                roles.iter().collect(),
+                format!("Choose Role for {}", profile_name),
+                |role| role.role_arn.clone(),
+            )
+            .map(Role::clone)
+            .map_err(Into::into)
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct FullProfileConfig {
     pub application: String,
     pub role: Option<String>,
     pub duration_seconds: Option<i64>,
+
+    /// ARN of a second role to assume (in a potentially different account)
+    /// using the credentials obtained from the Okta-federated role.
+    pub assume_role_arn: Option<String>,
+    pub external_id: Option<String>,
 }
 
 impl From<ProfileConfig> for FullProfileConfig {
@@ -103,6 +155,8 @@ impl From<ProfileConfig> for FullProfileConfig {
                 application,
                 role: None,
                 duration_seconds: None,
+                assume_role_arn: None,
+                external_id: None,
             },
         }
     }
@@ -112,8 +166,13 @@ impl From<ProfileConfig> for FullProfileConfig {
 pub struct Profile {
     pub name: String,
     pub application_name: String,
-    pub role: String,
+    /// The role to assume. If `None`, the role is resolved at credential time
+    /// from the organization's `role_mappings`, falling back to an
+    /// interactive prompt if no mapping matches.
+    pub role: Option<String>,
     pub duration_seconds: Option<i64>,
+    pub assume_role_arn: Option<String>,
+    pub external_id: Option<String>,
 }
 
 impl Profile {
@@ -128,18 +187,22 @@ impl Profile {
         Ok(Profile {
             name,
             application_name: full_profile_config.application,
-            role: full_profile_config
-                .role
-                .or(default_role)
-                .ok_or_else(|| anyhow!("No role found"))?,
+            role: full_profile_config.role.or(default_role),
             duration_seconds: full_profile_config
                 .duration_seconds
                 .or(default_duration_seconds),
+            assume_role_arn: full_profile_config.assume_role_arn,
+            external_id: full_profile_config.external_id,
         })
     }
 
-    #[instrument(skip(self, client), fields(organization=%client.base_url, profile=%self.name))]
-    pub async fn into_credentials(self, client: &OktaClient) -> Result<Credentials> {
+    #[instrument(skip(self, client, role_mappings, groups), fields(organization=%client.base_url, profile=%self.name))]
+    pub async fn into_credentials(
+        self,
+        client: &OktaClient,
+        role_mappings: Option<&HashMap<String, String>>,
+        groups: &[String],
+    ) -> Result<Credentials> {
         info!("Requesting tokens");
 
         let app_link = client
@@ -168,16 +231,19 @@ impl Profile {
 
         debug!("SAML Roles: {:?}", &roles);
 
-        let role: Role = roles
-            .into_iter()
-            .find(|r| r.role_name().map(|r| r == self.role).unwrap_or(false))
-            .ok_or_else(|| {
-                anyhow!(
-                    "No matching role ({}) found for profile {}",
-                    self.role,
-                    &self.name
-                )
-            })?;
+        let role: Role = match &self.role {
+            Some(role_name) => roles
+                .into_iter()
+                .find(|r| r.role_name().map(|r| r == role_name).unwrap_or(false))
+                .ok_or_else(|| {
+                    anyhow!(
+                        "No matching role ({}) found for profile {}",
+                        role_name,
+                        &self.name
+                    )
+                })?,
+            None => select_role(roles, role_mappings, groups, &self.name)?,
+        };
 
         trace!("Found role: {} for profile {}", role.role_arn, &self.name);
 
@@ -192,6 +258,53 @@ impl Profile {
 
         trace!("Credentials: {:?}", credentials);
 
-        Ok(credentials)
+        match &self.assume_role_arn {
+            Some(assume_role_arn) => {
+                self.assume_chained_role(assume_role_arn, credentials).await
+            }
+            None => Ok(credentials),
+        }
+    }
+
+    /// Uses credentials from the initial SAML-federated role to assume a
+    /// second, downstream role. This supports hub-and-spoke setups where the
+    /// Okta-federated role only grants permission to assume roles in other
+    /// accounts.
+    async fn assume_chained_role(
+        &self,
+        assume_role_arn: &str,
+        credentials: Credentials,
+    ) -> Result<Credentials> {
+        info!("Assuming chained role {}", assume_role_arn);
+
+        let provider = StaticProvider::new(
+            credentials.access_key_id,
+            credentials.secret_access_key,
+            Some(credentials.session_token),
+            None,
+        );
+        let client = StsClient::new_with(HttpClient::new()?, provider, Region::default());
+
+        let assumption_response = client
+            .assume_role(AssumeRoleRequest {
+                role_arn: assume_role_arn.to_string(),
+                role_session_name: self.name.clone(),
+                duration_seconds: self.duration_seconds,
+                external_id: self.external_id.clone(),
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| {
+                anyhow!(
+                    "Error assuming chained role {} for profile {} ({})",
+                    assume_role_arn,
+                    self.name,
+                    e
+                )
+            })?;
+
+        assumption_response
+            .credentials
+            .ok_or_else(|| anyhow!("Error fetching credentials from assumed chained role"))
     }
 }
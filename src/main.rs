@@ -1,15 +1,22 @@
+use oktaws::aws::credential_process::CredentialProcessOutput;
 use oktaws::aws::credentials::CredentialsStore;
 use oktaws::config::organization::OrganizationConfig;
 use oktaws::config::{oktaws_home, Config};
 use oktaws::okta::client::Client as OktaClient;
 
+use std::collections::{HashMap, HashSet};
 use std::convert::{TryFrom, TryInto};
 use std::env;
+use std::process::Command as ChildCommand;
 use std::sync::{Arc, Mutex};
 
 use anyhow::{anyhow, Error, Result};
+use chrono::Duration;
 use glob::Pattern;
-use log::{debug, info};
+use log::{debug, info, warn};
+use rusoto_core::{HttpClient, Region};
+use rusoto_credential::StaticProvider;
+use rusoto_sts::{Credentials as StsCredentials, GetCallerIdentityRequest, Sts, StsClient};
 use structopt::StructOpt;
 
 #[derive(StructOpt, Debug)]
@@ -26,6 +33,9 @@ struct Args {
 enum Command {
     Refresh(RefreshArgs),
     Init(InitArgs),
+    Cred(CredArgs),
+    Exec(ExecArgs),
+    Whoami(WhoamiArgs),
 }
 
 #[paw::main]
@@ -45,6 +55,9 @@ async fn main(args: Args) -> Result<()> {
     match args.cmd {
         Command::Refresh(args) => refresh(args).await,
         Command::Init(args) => init(args.try_into()?).await,
+        Command::Cred(args) => cred(args).await,
+        Command::Exec(args) => exec(args).await,
+        Command::Whoami(args) => whoami(args).await,
     }
 }
 
@@ -72,6 +85,14 @@ struct RefreshArgs {
     #[structopt(short = "f", long = "force-new")]
     #[cfg(not(target_os = "linux"))]
     pub force_new: bool,
+
+    /// Re-assumes roles even if cached credentials are still valid
+    #[structopt(long = "force")]
+    pub force: bool,
+
+    /// Minutes of validity a cached credential must have left to be reused
+    #[structopt(long = "refresh-margin", default_value = "15")]
+    pub refresh_margin: i64,
 }
 
 async fn refresh(args: RefreshArgs) -> Result<()> {
@@ -82,6 +103,8 @@ async fn refresh(args: RefreshArgs) -> Result<()> {
     // Set up a store for AWS credentials
     let credentials_store = Arc::new(Mutex::new(CredentialsStore::new()?));
 
+    let refresh_margin = Duration::minutes(args.refresh_margin);
+
     let mut organizations = config
         .into_organizations(args.organizations.clone())
         .peekable();
@@ -104,16 +127,32 @@ async fn refresh(args: RefreshArgs) -> Result<()> {
         )
         .await?;
 
+        let cached_credentials = if args.force {
+            HashMap::new()
+        } else {
+            credentials_store.lock().unwrap().profiles.all_sts_credentials()
+        };
+
         let credentials_map = organization
-            .into_credentials(&okta_client, args.profiles.clone())
+            .into_credentials(
+                &okta_client,
+                args.profiles.clone(),
+                &cached_credentials,
+                refresh_margin,
+            )
             .await;
 
-        for (name, creds) in credentials_map {
-            credentials_store
-                .lock()
-                .unwrap()
-                .profiles
-                .set_sts_credentials(name.clone(), creds.into())?;
+        for (name, result) in credentials_map {
+            match result {
+                Ok(creds) => {
+                    credentials_store
+                        .lock()
+                        .unwrap()
+                        .profiles
+                        .set_sts_credentials(name.clone(), creds.into())?;
+                }
+                Err(e) => warn!("Error refreshing profile {} ({})", name, e),
+            }
         }
     }
 
@@ -121,6 +160,232 @@ async fn refresh(args: RefreshArgs) -> Result<()> {
     store.save()
 }
 
+#[derive(StructOpt, Debug)]
+struct CredArgs {
+    /// Okta organization the profile belongs to
+    #[structopt(short = "o", long = "organization", parse(try_from_str))]
+    pub organization: Pattern,
+
+    /// Profile to fetch credentials for
+    #[structopt(short = "p", long = "profile", parse(try_from_str))]
+    pub profile: Pattern,
+}
+
+/// Resolves the single organization matching `organization` and the single
+/// profile within it matching `profile`, then assumes its role, erroring out
+/// if either pattern matches zero or more than one candidate. Used by
+/// subcommands that operate on exactly one profile (`cred`, `exec`) so that
+/// "no/ambiguous match" behavior can't drift between them.
+async fn resolve_one_profile_credentials(
+    organization: Pattern,
+    profile: Pattern,
+) -> Result<StsCredentials> {
+    let config = Config::new()?;
+
+    let mut organizations = config.into_organizations(organization.clone()).peekable();
+
+    let organization_config = organizations
+        .next()
+        .ok_or_else(|| anyhow!("No organization found called {}", organization))?;
+
+    if organizations.peek().is_some() {
+        return Err(anyhow!("More than one organization matched {}", organization));
+    }
+
+    let okta_client = OktaClient::new(
+        organization_config.name.clone(),
+        organization_config.username.clone(),
+        #[cfg(not(target_os = "linux"))]
+        false,
+    )
+    .await?;
+
+    let mut credentials_map = organization_config
+        .into_credentials(&okta_client, profile.clone(), &HashMap::new(), Duration::zero())
+        .await;
+
+    match credentials_map.len() {
+        0 => Err(anyhow!("No profile found called {}", profile)),
+        1 => credentials_map.drain().next().unwrap().1,
+        _ => Err(anyhow!("More than one profile matched {}", profile)),
+    }
+}
+
+/// Resolves a single profile's credentials and prints them to stdout in the
+/// JSON format expected by an AWS SDK `credential_process` hook, e.g.
+///
+/// ```text
+/// credential_process = oktaws cred -o myorg -p myprofile
+/// ```
+async fn cred(args: CredArgs) -> Result<()> {
+    let credentials = resolve_one_profile_credentials(args.organization, args.profile).await?;
+
+    let output = CredentialProcessOutput::try_from(credentials)?;
+
+    println!("{}", serde_json::to_string(&output)?);
+
+    Ok(())
+}
+
+#[derive(StructOpt, Debug)]
+struct ExecArgs {
+    /// Okta organization the profile belongs to
+    #[structopt(short = "o", long = "organization", parse(try_from_str))]
+    pub organization: Pattern,
+
+    /// Profile to assume credentials for
+    #[structopt(short = "p", long = "profile", parse(try_from_str))]
+    pub profile: Pattern,
+
+    /// Command (and arguments) to run with the credentials injected. Defaults to $SHELL.
+    #[structopt(last = true)]
+    pub command: Vec<String>,
+}
+
+/// Resolves a single profile's credentials and runs a child process with them
+/// injected as environment variables, without writing anything to the shared
+/// AWS credentials file.
+async fn exec(args: ExecArgs) -> Result<()> {
+    let credentials = resolve_one_profile_credentials(args.organization, args.profile).await?;
+
+    let mut command_and_args = args.command.into_iter();
+    let shell = env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string());
+    let program = command_and_args.next().unwrap_or(shell);
+
+    let status = ChildCommand::new(program)
+        .args(command_and_args)
+        .env("AWS_ACCESS_KEY_ID", &credentials.access_key_id)
+        .env("AWS_SECRET_ACCESS_KEY", &credentials.secret_access_key)
+        .env("AWS_SESSION_TOKEN", &credentials.session_token)
+        .env("AWS_REGION", Region::default().name())
+        .status()?;
+
+    std::process::exit(status.code().unwrap_or(1));
+}
+
+#[derive(StructOpt, Debug)]
+struct WhoamiArgs {
+    /// Okta organization(s) to use
+    #[structopt(
+        short = "o",
+        long = "organizations",
+        default_value = "*",
+        parse(try_from_str)
+    )]
+    pub organizations: Pattern,
+
+    /// Profile(s) to check
+    #[structopt(
+        short = "p",
+        long = "profiles",
+        default_value = "*",
+        parse(try_from_str)
+    )]
+    pub profiles: Pattern,
+}
+
+/// For each selected profile, reads the stored credentials (assuming the role
+/// afresh if they're missing or expired) and calls STS `GetCallerIdentity`,
+/// printing the account, ARN and user ID they resolve to.
+async fn whoami(args: WhoamiArgs) -> Result<()> {
+    let config = Config::new()?;
+
+    let credentials_store = CredentialsStore::new()?;
+    let cached_credentials = credentials_store.profiles.all_sts_credentials();
+
+    let mut organizations = config
+        .into_organizations(args.organizations.clone())
+        .peekable();
+
+    if organizations.peek().is_none() {
+        return Err(anyhow!(
+            "No organizations found called {}",
+            args.organizations
+        ));
+    }
+
+    let mut any_failed = false;
+
+    for organization in organizations {
+        let okta_client = OktaClient::new(
+            organization.name.clone(),
+            organization.username.clone(),
+            #[cfg(not(target_os = "linux"))]
+            false,
+        )
+        .await?;
+
+        let own_profile_names: HashSet<String> = organization.profiles.keys().cloned().collect();
+
+        let mut credentials_map = organization
+            .into_credentials(
+                &okta_client,
+                args.profiles.clone(),
+                &cached_credentials,
+                Duration::minutes(15),
+            )
+            .await;
+
+        for (name, credentials) in &cached_credentials {
+            if own_profile_names.contains(name)
+                && args.profiles.matches(name)
+                && !credentials_map.contains_key(name)
+            {
+                if let Ok(credentials) = StsCredentials::try_from(credentials.clone()) {
+                    credentials_map.insert(name.clone(), Ok(credentials));
+                }
+            }
+        }
+
+        for (name, result) in credentials_map {
+            let identity = match result {
+                Ok(credentials) => get_caller_identity(credentials).await,
+                Err(e) => Err(e),
+            };
+
+            match identity {
+                Ok((account, arn, user_id)) => {
+                    println!("{}: account={} arn={} user_id={}", name, account, arn, user_id);
+                }
+                Err(e) => {
+                    any_failed = true;
+                    info!("Error verifying identity for profile {} ({})", name, e);
+                }
+            }
+        }
+    }
+
+    if any_failed {
+        return Err(anyhow!(
+            "One or more profiles failed identity verification"
+        ));
+    }
+
+    Ok(())
+}
+
+async fn get_caller_identity(credentials: StsCredentials) -> Result<(String, String, String)> {
+    let provider = StaticProvider::new(
+        credentials.access_key_id,
+        credentials.secret_access_key,
+        Some(credentials.session_token),
+        None,
+    );
+    let client = StsClient::new_with(HttpClient::new()?, provider, Region::default());
+
+    let identity = client
+        .get_caller_identity(GetCallerIdentityRequest {})
+        .await?;
+
+    Ok((
+        identity.account.ok_or_else(|| anyhow!("No account in response"))?,
+        identity.arn.ok_or_else(|| anyhow!("No ARN in response"))?,
+        identity
+            .user_id
+            .ok_or_else(|| anyhow!("No user ID in response"))?,
+    ))
+}
+
 #[derive(StructOpt, Debug)]
 struct InitArgs {
     /// Okta organization to use
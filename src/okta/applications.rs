@@ -13,6 +13,17 @@ pub struct AppLink {
     pub app_name: String,
 }
 
+#[derive(Deserialize, Debug, Clone)]
+pub struct GroupProfile {
+    pub name: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Group {
+    pub id: String,
+    pub profile: GroupProfile,
+}
+
 impl Client {
     pub async fn app_links(&self, user_id: Option<&str>) -> Result<Vec<AppLink>> {
         self.get(&format!(
@@ -22,6 +33,11 @@ impl Client {
         .await
     }
 
+    pub async fn groups(&self, user_id: Option<&str>) -> Result<Vec<Group>> {
+        self.get(&format!("api/v1/users/{}/groups", user_id.unwrap_or("me")))
+            .await
+    }
+
     pub async fn roles(&self, link: &AppLink) -> Result<Vec<Role>> {
         self.get_saml_response(link.link_url.clone())
             .await